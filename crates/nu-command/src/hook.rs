@@ -1,10 +1,16 @@
 use crate::util::{get_guaranteed_cwd, report_error, report_error_new};
 use miette::Result;
 use nu_engine::{eval_block, eval_block_with_early_return};
+use nu_glob::Pattern;
 use nu_parser::parse;
 use nu_protocol::ast::PathMember;
 use nu_protocol::engine::{EngineState, Stack, StateWorkingSet};
 use nu_protocol::{BlockId, PipelineData, PositionalArg, ShellError, Span, Type, Value, VarId};
+use std::collections::BTreeSet;
+
+// Metacharacters that mark an `env_change` key as a glob pattern rather than a plain
+// environment variable name.
+const GLOB_METACHARS: [char; 3] = ['*', '?', '['];
 
 pub fn eval_env_change_hook(
     env_change_hook: Option<Value>,
@@ -19,28 +25,16 @@ pub fn eval_env_change_hook(
                 ..
             } => {
                 for (env_name, hook_value) in env_names.iter().zip(hook_values.iter()) {
-                    let before = engine_state
-                        .previous_env_vars
-                        .get(env_name)
-                        .cloned()
-                        .unwrap_or_default();
-
-                    let after = stack
-                        .get_env_var(engine_state, env_name)
-                        .unwrap_or_default();
-
-                    if before != after {
-                        eval_hook(
+                    if env_name.contains(GLOB_METACHARS) {
+                        run_env_change_hook_for_glob(env_name, hook_value, engine_state, stack)?;
+                    } else {
+                        run_env_change_hook_for_name(
+                            env_name,
+                            hook_value,
                             engine_state,
                             stack,
                             None,
-                            vec![("$before".into(), before), ("$after".into(), after.clone())],
-                            hook_value,
                         )?;
-
-                        engine_state
-                            .previous_env_vars
-                            .insert(env_name.to_string(), after);
                     }
                 }
             }
@@ -56,6 +50,156 @@ pub fn eval_env_change_hook(
     Ok(())
 }
 
+// Matches `pattern` (an `env_change` key containing glob metacharacters) against both the
+// live environment and any previously-seen names, so a variable that gets unset still has
+// its removal detected, and fires the hook once per changed matching variable with an
+// extra `$name` argument.
+fn run_env_change_hook_for_glob(
+    pattern: &str,
+    hook_value: &Value,
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+) -> Result<(), ShellError> {
+    let span = hook_value.span()?;
+    let glob = Pattern::new(pattern).map_err(|_| ShellError::TypeMismatch {
+        err_message: format!("'{pattern}' is not a valid glob pattern for the 'env_change' hook"),
+        span,
+    })?;
+
+    let live_names = stack.get_env_var_names(engine_state).into_iter();
+    let previous_names = engine_state.previous_env_vars.keys().cloned();
+    let matching_names = glob_matching_names(&glob, live_names, previous_names);
+
+    for name in matching_names {
+        run_env_change_hook_for_name(&name, hook_value, engine_state, stack, Some(&name))?;
+    }
+
+    Ok(())
+}
+
+fn glob_matching_names(
+    glob: &Pattern,
+    live_names: impl Iterator<Item = String>,
+    previous_names: impl Iterator<Item = String>,
+) -> BTreeSet<String> {
+    live_names
+        .chain(previous_names)
+        .filter(|name| glob.matches(name))
+        .collect()
+}
+
+// Fires the hook for a single concrete environment variable name if it changed, keeping
+// `previous_env_vars` bookkeeping per variable. `glob_name` is passed through as the extra
+// `$name` argument when the key that matched this variable was a glob pattern.
+fn run_env_change_hook_for_name(
+    env_name: &str,
+    hook_value: &Value,
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    glob_name: Option<&str>,
+) -> Result<(), ShellError> {
+    let before = engine_state
+        .previous_env_vars
+        .get(env_name)
+        .cloned()
+        .unwrap_or_default();
+
+    let after = stack
+        .get_env_var(engine_state, env_name)
+        .unwrap_or_default();
+
+    if before != after {
+        let mut arguments = vec![("$before".into(), before), ("$after".into(), after.clone())];
+
+        if let Some(name) = glob_name {
+            arguments.push(("$name".into(), Value::string(name, hook_value.span()?)));
+        }
+
+        eval_hook(engine_state, stack, None, arguments, hook_value)?;
+
+        engine_state
+            .previous_env_vars
+            .insert(env_name.to_string(), after);
+    }
+
+    Ok(())
+}
+
+// Pre-populates `$cmd_name` and `$suggestions` (nearest known command names by edit
+// distance) before running the usual hook machinery.
+pub fn eval_command_not_found_hook(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    cmd_name: &str,
+    hook: &Value,
+) -> Result<PipelineData, ShellError> {
+    let span = hook.span()?;
+    let suggestions = command_not_found_suggestions(engine_state, cmd_name, span);
+
+    eval_hook(
+        engine_state,
+        stack,
+        None,
+        vec![
+            ("$cmd_name".into(), Value::string(cmd_name, span)),
+            ("$suggestions".into(), Value::list(suggestions, span)),
+        ],
+        hook,
+    )
+}
+
+fn command_not_found_suggestions(
+    engine_state: &EngineState,
+    cmd_name: &str,
+    span: Span,
+) -> Vec<Value> {
+    let candidate_names = engine_state.get_decls_sorted(false).map(|(name, _)| name);
+    closest_command_names(cmd_name.as_bytes(), candidate_names, span)
+}
+
+// Keeps only candidates within `max(cmd_name.len(), 3) / 3` edits, sorted ascending by
+// distance.
+fn closest_command_names(
+    cmd_name: &[u8],
+    candidate_names: impl Iterator<Item = Vec<u8>>,
+    span: Span,
+) -> Vec<Value> {
+    let max_distance = cmd_name.len().max(3) / 3;
+
+    let mut candidates: Vec<(usize, Vec<u8>)> = candidate_names
+        .map(|name| (levenshtein_distance(cmd_name, &name), name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    candidates.sort_by_key(|(distance, _)| *distance);
+
+    candidates
+        .into_iter()
+        .map(|(_, name)| Value::string(String::from_utf8_lossy(&name), span))
+        .collect()
+}
+
+// Classic two-row DP Levenshtein edit distance between two byte strings.
+fn levenshtein_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    let mut curr_row = vec![0usize; b.len() + 1];
+
+    for (i, &a_byte) in a.iter().enumerate() {
+        curr_row[0] = i + 1;
+
+        for (j, &b_byte) in b.iter().enumerate() {
+            let substitution_cost = usize::from(a_byte != b_byte);
+            curr_row[j + 1] = (prev_row[j + 1] + 1) // deletion
+                .min(curr_row[j] + 1) // insertion
+                .min(prev_row[j] + substitution_cost); // substitution
+        }
+
+        std::mem::swap(&mut prev_row, &mut curr_row);
+    }
+
+    prev_row[b.len()]
+}
+
 pub fn eval_hook(
     engine_state: &mut EngineState,
     stack: &mut Stack,
@@ -69,6 +213,7 @@ pub fn eval_hook(
     // {
     //     condition: {|before, after| ... }  # block that evaluates to true/false
     //     code: # block or a string
+    //     on_error: "report"  # "report" (default), "ignore", or "abort"; string hooks only
     // }
     // The condition block will be run to check whether the main hook (in `code`) should be run.
     // If it returns true (the default if a condition block is not specified), the hook should be run.
@@ -145,6 +290,8 @@ pub fn eval_hook(
                 };
 
             if do_run_hook {
+                let on_error = hook_on_error_mode(value, value_span)?;
+
                 match value.clone().follow_cell_path(&[code_path], false)? {
                     Value::String {
                         val,
@@ -192,18 +339,25 @@ pub fn eval_hook(
                             })
                             .collect();
 
-                        match eval_block(engine_state, stack, &block, input, false, false) {
+                        let block_result =
+                            eval_block(engine_state, stack, &block, input, false, false);
+
+                        for var_id in var_ids.iter() {
+                            stack.vars.remove(var_id);
+                        }
+
+                        match block_result {
                             Ok(pipeline_data) => {
                                 output = pipeline_data;
                             }
+                            Err(err) if on_error == "abort" => {
+                                return Err(err);
+                            }
+                            Err(err) if on_error == "ignore" => {}
                             Err(err) => {
                                 report_error_new(engine_state, &err);
                             }
                         }
-
-                        for var_id in var_ids.iter() {
-                            stack.vars.remove(var_id);
-                        }
                     }
                     Value::Block {
                         val: block_id,
@@ -286,6 +440,34 @@ pub fn eval_hook(
     Ok(output)
 }
 
+// Reads the hook record's `on_error` field, defaulting to "report" when absent. Anything
+// else must be one of the recognized modes, just like `condition`/`code` reject anything
+// other than their expected shapes.
+fn hook_on_error_mode(value: &Value, value_span: Span) -> Result<String, ShellError> {
+    let on_error_path = PathMember::String {
+        val: "on_error".to_string(),
+        span: value_span,
+        optional: true,
+    };
+
+    match value.clone().follow_cell_path(&[on_error_path], false)? {
+        Value::Nothing { .. } => Ok("report".to_string()),
+        Value::String { val, .. } if matches!(val.as_str(), "report" | "ignore" | "abort") => {
+            Ok(val)
+        }
+        Value::String { val, span } => Err(ShellError::UnsupportedConfigValue(
+            "'report', 'ignore', or 'abort'".to_string(),
+            val,
+            span,
+        )),
+        other => Err(ShellError::UnsupportedConfigValue(
+            "'report', 'ignore', or 'abort'".to_string(),
+            format!("{}", other.get_type()),
+            other.span()?,
+        )),
+    }
+}
+
 fn run_hook_block(
     engine_state: &EngineState,
     stack: &mut Stack,
@@ -339,3 +521,146 @@ fn run_hook_block(
     }
     Ok(pipeline_data)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hook_record(cols: Vec<&str>, vals: Vec<Value>) -> Value {
+        Value::Record {
+            cols: cols.into_iter().map(String::from).collect(),
+            vals,
+            span: Span::test_data(),
+        }
+    }
+
+    #[test]
+    fn hook_on_error_mode_defaults_to_report() {
+        let hook = hook_record(vec!["code"], vec![Value::string("true", Span::test_data())]);
+
+        assert_eq!(
+            hook_on_error_mode(&hook, Span::test_data()).unwrap(),
+            "report"
+        );
+    }
+
+    #[test]
+    fn hook_on_error_mode_reads_ignore_and_abort() {
+        let ignore_hook = hook_record(
+            vec!["code", "on_error"],
+            vec![
+                Value::string("true", Span::test_data()),
+                Value::string("ignore", Span::test_data()),
+            ],
+        );
+        let abort_hook = hook_record(
+            vec!["code", "on_error"],
+            vec![
+                Value::string("true", Span::test_data()),
+                Value::string("abort", Span::test_data()),
+            ],
+        );
+
+        assert_eq!(
+            hook_on_error_mode(&ignore_hook, Span::test_data()).unwrap(),
+            "ignore"
+        );
+        assert_eq!(
+            hook_on_error_mode(&abort_hook, Span::test_data()).unwrap(),
+            "abort"
+        );
+    }
+
+    #[test]
+    fn hook_on_error_mode_rejects_unrecognized_string() {
+        let hook = hook_record(
+            vec!["code", "on_error"],
+            vec![
+                Value::string("true", Span::test_data()),
+                Value::string("Abort", Span::test_data()),
+            ],
+        );
+
+        assert!(hook_on_error_mode(&hook, Span::test_data()).is_err());
+    }
+
+    #[test]
+    fn hook_on_error_mode_rejects_non_string() {
+        let hook = hook_record(
+            vec!["code", "on_error"],
+            vec![
+                Value::string("true", Span::test_data()),
+                Value::boolean(true, Span::test_data()),
+            ],
+        );
+
+        assert!(hook_on_error_mode(&hook, Span::test_data()).is_err());
+    }
+
+    #[test]
+    fn glob_matching_names_includes_vars_unset_since_last_seen() {
+        let glob = Pattern::new("LC_*").unwrap();
+        let live_names = vec!["LC_ALL".to_string()].into_iter();
+        let previous_names = vec!["LC_ALL".to_string(), "LC_TIME".to_string()].into_iter();
+
+        let matching = glob_matching_names(&glob, live_names, previous_names);
+
+        assert_eq!(
+            matching,
+            BTreeSet::from(["LC_ALL".to_string(), "LC_TIME".to_string()])
+        );
+    }
+
+    #[test]
+    fn glob_matching_names_ignores_non_matching_vars() {
+        let glob = Pattern::new("LC_*").unwrap();
+        let live_names = vec!["LC_ALL".to_string(), "PATH".to_string()].into_iter();
+        let previous_names = std::iter::empty();
+
+        let matching = glob_matching_names(&glob, live_names, previous_names);
+
+        assert_eq!(matching, BTreeSet::from(["LC_ALL".to_string()]));
+    }
+
+    #[test]
+    fn levenshtein_distance_equal_strings_is_zero() {
+        assert_eq!(levenshtein_distance(b"ls", b"ls"), 0);
+    }
+
+    #[test]
+    fn levenshtein_distance_against_empty_is_the_other_len() {
+        assert_eq!(levenshtein_distance(b"", b"ls"), 2);
+        assert_eq!(levenshtein_distance(b"ls", b""), 2);
+    }
+
+    #[test]
+    fn levenshtein_distance_counts_edits() {
+        assert_eq!(levenshtein_distance(b"sl", b"ls"), 2); // substitute both
+        assert_eq!(levenshtein_distance(b"l", b"ls"), 1); // insert one
+    }
+
+    #[test]
+    fn closest_command_names_drops_candidates_past_the_threshold() {
+        // cmd_name.len() == 2, so max_distance == 2.max(3) / 3 == 1
+        let candidates = vec![b"lsp".to_vec(), b"lsz".to_vec(), b"table".to_vec()];
+        let names: Vec<String> =
+            closest_command_names(b"ls", candidates.into_iter(), Span::test_data())
+                .into_iter()
+                .map(|v| v.as_string().unwrap())
+                .collect();
+
+        assert_eq!(names, vec!["lsp".to_string(), "lsz".to_string()]);
+    }
+
+    #[test]
+    fn closest_command_names_sorts_by_ascending_distance() {
+        let candidates = vec![b"lsz".to_vec(), b"ls".to_vec()];
+        let names: Vec<String> =
+            closest_command_names(b"ls", candidates.into_iter(), Span::test_data())
+                .into_iter()
+                .map(|v| v.as_string().unwrap())
+                .collect();
+
+        assert_eq!(names, vec!["ls".to_string(), "lsz".to_string()]);
+    }
+}